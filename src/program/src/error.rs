@@ -0,0 +1,35 @@
+//! Error type
+
+use solana_program::program_error::ProgramError;
+use thiserror::Error;
+
+/// Errors that may be returned by the Faucet program
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum FaucetError {
+    /// Failed to unpack instruction data
+    #[error("Failed to unpack instruction data")]
+    InvalidInstruction,
+
+    /// Non-admin caller minted more than `cap_per_slice` within the current `time_slice`
+    #[error("Claimant exceeded their rate limit for the current time slice")]
+    RateLimitExceeded,
+
+    /// Non-admin caller requested more than the faucet's per-ix `amount` and did not set
+    /// `clamp_to_cap`
+    #[error("Requested amount exceeds the faucet's per-ix cap")]
+    AmountExceedsCap,
+
+    /// `MintTokens` was attempted while the faucet is paused
+    #[error("Faucet is paused")]
+    FaucetPaused,
+
+    /// Minting would push `total_minted` past `max_total_minted`
+    #[error("Faucet's lifetime supply ceiling has been reached")]
+    SupplyCeilingReached,
+}
+
+impl From<FaucetError> for ProgramError {
+    fn from(e: FaucetError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
@@ -1,4 +1,6 @@
-use std::convert::TryInto;
+//! Instruction types
+
+use std::convert::{TryFrom, TryInto};
 use std::mem::size_of;
 
 use crate::error::FaucetError;
@@ -6,6 +8,7 @@ use solana_program::program_error::ProgramError;
 use solana_program::program_option::COption;
 use solana_program::pubkey::Pubkey;
 
+/// Instructions supported by the Faucet program
 #[repr(C)]
 #[derive(Clone, Debug, PartialEq)]
 pub enum FaucetInstruction {
@@ -21,21 +24,58 @@ pub enum FaucetInstruction {
         admin: COption<Pubkey>,
         /// all other accounts may only mint this amount per ix
         amount: u64,
+        /// length, in seconds, of the rolling window used to rate-limit non-admin claimants
+        time_slice: i64,
+        /// total amount a single claimant may mint within one `time_slice`
+        cap_per_slice: u64,
+        /// hard lifetime cap on tokens minted by this faucet, unlimited if `COption::None`
+        max_total_minted: COption<u64>,
     },
     /// Mints Tokens
     ///
     /// 0. `[]` The mint authority - Program Derived Address
-    /// 1. `[writable]` Token Mint Account
-    /// 2. `[writable]` Destination Account
-    /// 3. `[]` The SPL Token Program
-    /// 4. `[optional/signer]` Admin Account
-    MintTokens { amount: u64 },
+    /// 1. `[writable]` Faucet Account
+    /// 2. `[writable]` Token Mint Account
+    /// 3. `[writable]` Destination Account
+    /// 4. `[]` The SPL Token Program
+    /// 5. `[optional signer]` Admin Account - the slot must always be filled; it grants admin
+    ///    rights only when it is the faucet's admin and signs
+    /// 6. `[writable/signer]` Payer - funds the lazy-created claimant rate-limit record
+    /// 7. `[writable]` Claimant rate-limit record - Program Derived Address, seeded by faucet + destination owner
+    /// 8. `[]` The System Program, for lazy init of the claimant record
+    /// 9. `[]` The Clock sysvar
+    /// 10. `[optional]` The SPL Memo program - required when `clamp_to_cap` is set and `amount` exceeds the faucet's per-ix limit
+    MintTokens {
+        /// amount of tokens to mint
+        amount: u64,
+        /// if `amount` exceeds the faucet's per-ix limit, mint the limit and attach an explanatory
+        /// memo instead of failing the instruction
+        clamp_to_cap: bool,
+    },
     /// Closes the faucet, can only be done if the faucet has an admin key
     ///
     /// 0. `[signer]` Admin account
     /// 1. `[writable]` Destination account for rent
     /// 2. `[writable]` Faucet account
     CloseFaucet,
+    /// Rotates the admin and/or changes the per-ix amount of an existing faucet
+    ///
+    /// 0. `[signer]` Current admin account
+    /// 1. `[writable]` Faucet account
+    UpdateFaucet {
+        /// leave the admin unchanged if `None`; `Some(COption::None)` clears it, `Some(COption::Some(key))` rotates it
+        new_admin: Option<COption<Pubkey>>,
+        /// leave the per-ix amount unchanged if `None`
+        new_amount: Option<u64>,
+    },
+    /// Pauses or unpauses minting; `MintTokens` fails while paused
+    ///
+    /// 0. `[signer]` Admin account
+    /// 1. `[writable]` Faucet account
+    SetPaused {
+        /// new paused state for the faucet
+        paused: bool,
+    },
 }
 
 impl FaucetInstruction {
@@ -50,7 +90,27 @@ impl FaucetInstruction {
                     .and_then(|slice| slice.try_into().ok())
                     .map(u64::from_le_bytes)
                     .ok_or(FaucetError::InvalidInstruction)?;
-                Self::InitFaucet { admin, amount }
+                let rest = &rest[8..];
+                let time_slice = rest
+                    .get(..8)
+                    .and_then(|slice| slice.try_into().ok())
+                    .map(i64::from_le_bytes)
+                    .ok_or(FaucetError::InvalidInstruction)?;
+                let rest = &rest[8..];
+                let cap_per_slice = rest
+                    .get(..8)
+                    .and_then(|slice| slice.try_into().ok())
+                    .map(u64::from_le_bytes)
+                    .ok_or(FaucetError::InvalidInstruction)?;
+                let rest = &rest[8..];
+                let (max_total_minted, _rest) = Self::unpack_u64_coption(rest)?;
+                Self::InitFaucet {
+                    admin,
+                    amount,
+                    time_slice,
+                    cap_per_slice,
+                    max_total_minted,
+                }
             }
             1 => {
                 let amount = rest
@@ -58,39 +118,140 @@ impl FaucetInstruction {
                     .and_then(|slice| slice.try_into().ok())
                     .map(u64::from_le_bytes)
                     .ok_or(FaucetError::InvalidInstruction)?;
-                Self::MintTokens { amount }
+                let rest = &rest[8..];
+                let clamp_to_cap = match rest.first() {
+                    Some(&0) => false,
+                    Some(&1) => true,
+                    _ => return Err(FaucetError::InvalidInstruction.into()),
+                };
+                Self::MintTokens {
+                    amount,
+                    clamp_to_cap,
+                }
             }
             2 => Self::CloseFaucet,
+            3 => {
+                let (new_admin, rest) = Self::unpack_admin_change(rest)?;
+                let (new_amount, _rest) = Self::unpack_u64_option(rest)?;
+                Self::UpdateFaucet {
+                    new_admin,
+                    new_amount,
+                }
+            }
+            4 => {
+                let paused = match rest.first() {
+                    Some(&0) => false,
+                    Some(&1) => true,
+                    _ => return Err(FaucetError::InvalidInstruction.into()),
+                };
+                Self::SetPaused { paused }
+            }
             _ => return Err(FaucetError::InvalidInstruction.into()),
         })
     }
 
+    /// Packs a [FaucetInstruction](enum.FaucetInstruction.html) into a byte buffer.
     pub fn pack(&self) -> Vec<u8> {
         let mut buf = Vec::with_capacity(size_of::<Self>());
         match self {
-            Self::InitFaucet { ref admin, amount } => {
+            Self::InitFaucet {
+                ref admin,
+                amount,
+                time_slice,
+                cap_per_slice,
+                ref max_total_minted,
+            } => {
                 buf.push(0);
                 Self::pack_pubkey_option(admin, &mut buf);
                 buf.extend_from_slice(&amount.to_le_bytes());
+                buf.extend_from_slice(&time_slice.to_le_bytes());
+                buf.extend_from_slice(&cap_per_slice.to_le_bytes());
+                Self::pack_u64_coption(max_total_minted, &mut buf);
             }
-            Self::MintTokens { amount } => {
+            Self::MintTokens {
+                amount,
+                clamp_to_cap,
+            } => {
                 buf.push(1);
                 buf.extend_from_slice(&amount.to_le_bytes());
+                buf.push(*clamp_to_cap as u8);
             }
             Self::CloseFaucet => {
                 buf.push(2);
             }
+            Self::UpdateFaucet {
+                ref new_admin,
+                new_amount,
+            } => {
+                buf.push(3);
+                Self::pack_admin_change(new_admin, &mut buf);
+                Self::pack_u64_option(new_amount, &mut buf);
+            }
+            Self::SetPaused { paused } => {
+                buf.push(4);
+                buf.push(*paused as u8);
+            }
         }
 
         buf
     }
 
+    fn unpack_u64_coption(input: &[u8]) -> Result<(COption<u64>, &[u8]), ProgramError> {
+        match input.split_first() {
+            Option::Some((&0, rest)) => Ok((COption::None, rest)),
+            Option::Some((&1, rest)) => {
+                let value = rest
+                    .get(..8)
+                    .and_then(|slice| slice.try_into().ok())
+                    .map(u64::from_le_bytes)
+                    .ok_or(FaucetError::InvalidInstruction)?;
+                Ok((COption::Some(value), &rest[8..]))
+            }
+            _ => Err(FaucetError::InvalidInstruction.into()),
+        }
+    }
+
+    fn pack_u64_coption(value: &COption<u64>, buf: &mut Vec<u8>) {
+        match *value {
+            COption::Some(amount) => {
+                buf.push(1);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            COption::None => buf.push(0),
+        }
+    }
+
+    fn unpack_u64_option(input: &[u8]) -> Result<(Option<u64>, &[u8]), ProgramError> {
+        match input.split_first() {
+            Option::Some((&0, rest)) => Ok((None, rest)),
+            Option::Some((&1, rest)) => {
+                let value = rest
+                    .get(..8)
+                    .and_then(|slice| slice.try_into().ok())
+                    .map(u64::from_le_bytes)
+                    .ok_or(FaucetError::InvalidInstruction)?;
+                Ok((Some(value), &rest[8..]))
+            }
+            _ => Err(FaucetError::InvalidInstruction.into()),
+        }
+    }
+
+    fn pack_u64_option(value: &Option<u64>, buf: &mut Vec<u8>) {
+        match *value {
+            Some(amount) => {
+                buf.push(1);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            None => buf.push(0),
+        }
+    }
+
     fn unpack_pubkey_option(input: &[u8]) -> Result<(COption<Pubkey>, &[u8]), ProgramError> {
         match input.split_first() {
             Option::Some((&0, rest)) => Ok((COption::None, rest)),
             Option::Some((&1, rest)) if rest.len() >= 32 => {
                 let (key, rest) = rest.split_at(32);
-                let pk = Pubkey::new(key);
+                let pk = Pubkey::try_from(key).map_err(|_| FaucetError::InvalidInstruction)?;
                 Ok((COption::Some(pk), rest))
             }
             _ => Err(FaucetError::InvalidInstruction.into()),
@@ -106,6 +267,33 @@ impl FaucetInstruction {
             COption::None => buf.push(0),
         }
     }
+
+    /// Unpacks `UpdateFaucet`'s `new_admin`, a three-way choice between "leave unchanged" (0),
+    /// "clear" (1), and "rotate to the following pubkey" (2) - distinct from `COption`'s own
+    /// None/Some so that "no change" and "explicitly clear the admin" can't be confused.
+    fn unpack_admin_change(input: &[u8]) -> Result<(Option<COption<Pubkey>>, &[u8]), ProgramError> {
+        match input.split_first() {
+            Option::Some((&0, rest)) => Ok((None, rest)),
+            Option::Some((&1, rest)) => Ok((Some(COption::None), rest)),
+            Option::Some((&2, rest)) if rest.len() >= 32 => {
+                let (key, rest) = rest.split_at(32);
+                let pk = Pubkey::try_from(key).map_err(|_| FaucetError::InvalidInstruction)?;
+                Ok((Some(COption::Some(pk)), rest))
+            }
+            _ => Err(FaucetError::InvalidInstruction.into()),
+        }
+    }
+
+    fn pack_admin_change(value: &Option<COption<Pubkey>>, buf: &mut Vec<u8>) {
+        match *value {
+            None => buf.push(0),
+            Some(COption::None) => buf.push(1),
+            Some(COption::Some(ref key)) => {
+                buf.push(2);
+                buf.extend_from_slice(&key.to_bytes());
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -114,37 +302,91 @@ mod test {
 
     #[test]
     fn test_instruction_unpacking() {
-        // 1 tag, 1 admin, 8 amount
-        let check = FaucetInstruction::unpack(&[0, 0, 7, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+        // 1 tag, 1 admin, 8 amount, 8 time_slice, 8 cap_per_slice, 1 max_total_minted (absent)
+        let check = FaucetInstruction::unpack(&[
+            0, 0, 7, 0, 0, 0, 0, 0, 0, 0, 60, 0, 0, 0, 0, 0, 0, 0, 9, 0, 0, 0, 0, 0, 0, 0, 0,
+        ])
+        .unwrap();
         assert_eq!(
             FaucetInstruction::InitFaucet {
                 admin: COption::None,
-                amount: 7
+                amount: 7,
+                time_slice: 60,
+                cap_per_slice: 9,
+                max_total_minted: COption::None,
             },
             check
         );
 
-        // 1 tag, 33 admin, 8 amount
+        // 1 tag, 33 admin, 8 amount, 8 time_slice, 8 cap_per_slice, 9 max_total_minted (present)
         let check = FaucetInstruction::unpack(&[
             0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
-            1, 1, 1, 1, 1, 7, 3, 0, 0, 0, 0, 0, 0,
+            1, 1, 1, 1, 1, 7, 3, 0, 0, 0, 0, 0, 0, 60, 0, 0, 0, 0, 0, 0, 0, 9, 0, 0, 0, 0, 0, 0, 0,
+            1, 200, 0, 0, 0, 0, 0, 0, 0,
         ])
         .unwrap();
         assert_eq!(
             FaucetInstruction::InitFaucet {
-                admin: COption::Some(Pubkey::new(&[1u8; 32])),
-                amount: 775
+                admin: COption::Some(Pubkey::try_from([1u8; 32].as_slice()).unwrap()),
+                amount: 775,
+                time_slice: 60,
+                cap_per_slice: 9,
+                max_total_minted: COption::Some(200),
             },
             check
         );
 
-        // 1 tag,  8 amount
-        let check = FaucetInstruction::unpack(&[1, 7, 3, 0, 0, 0, 0, 0, 0]).unwrap();
-        assert_eq!(FaucetInstruction::MintTokens { amount: 775 }, check);
+        // 1 tag,  8 amount, 1 clamp_to_cap
+        let check = FaucetInstruction::unpack(&[1, 7, 3, 0, 0, 0, 0, 0, 0, 1]).unwrap();
+        assert_eq!(
+            FaucetInstruction::MintTokens {
+                amount: 775,
+                clamp_to_cap: true,
+            },
+            check
+        );
 
         // 1 tag
         let check = FaucetInstruction::unpack(&[2]).unwrap();
         assert_eq!(FaucetInstruction::CloseFaucet, check);
+
+        // 1 tag, 1 new_admin (unchanged), 1 new_amount (absent)
+        let check = FaucetInstruction::unpack(&[3, 0, 0]).unwrap();
+        assert_eq!(
+            FaucetInstruction::UpdateFaucet {
+                new_admin: None,
+                new_amount: None,
+            },
+            check
+        );
+
+        // 1 tag, 1 new_admin (cleared), 1 new_amount (absent)
+        let check = FaucetInstruction::unpack(&[3, 1, 0]).unwrap();
+        assert_eq!(
+            FaucetInstruction::UpdateFaucet {
+                new_admin: Some(COption::None),
+                new_amount: None,
+            },
+            check
+        );
+
+        // 1 tag, 33 new_admin (rotated), 9 new_amount (present)
+        let mut buf = vec![3, 2];
+        buf.extend_from_slice(&[1u8; 32]);
+        buf.push(1);
+        buf.extend_from_slice(&u64::to_le_bytes(775));
+        let check = FaucetInstruction::unpack(&buf).unwrap();
+        assert_eq!(
+            FaucetInstruction::UpdateFaucet {
+                new_admin: Some(COption::Some(Pubkey::try_from([1u8; 32].as_slice()).unwrap())),
+                new_amount: Some(775),
+            },
+            check
+        );
+
+        // 1 tag, 1 paused
+        let check = FaucetInstruction::unpack(&[4, 1]).unwrap();
+        assert_eq!(FaucetInstruction::SetPaused { paused: true }, check);
     }
 
     #[test]
@@ -152,34 +394,84 @@ mod test {
         let check = FaucetInstruction::InitFaucet {
             admin: COption::None,
             amount: 900,
+            time_slice: 3600,
+            cap_per_slice: 100,
+            max_total_minted: COption::None,
         };
 
         let packed = check.pack();
         let mut expect = vec![0, 0];
         expect.extend_from_slice(&u64::to_le_bytes(900));
+        expect.extend_from_slice(&i64::to_le_bytes(3600));
+        expect.extend_from_slice(&u64::to_le_bytes(100));
+        expect.push(0);
         assert_eq!(packed, expect);
 
         let check = FaucetInstruction::InitFaucet {
-            admin: COption::Some(Pubkey::new(&[1u8; 32])),
+            admin: COption::Some(Pubkey::try_from([1u8; 32].as_slice()).unwrap()),
             amount: 900,
+            time_slice: 3600,
+            cap_per_slice: 100,
+            max_total_minted: COption::Some(5_000),
         };
 
         let packed = check.pack();
         let mut expect = vec![0, 1];
         expect.extend_from_slice(&[1u8; 32]);
         expect.extend_from_slice(&u64::to_le_bytes(900));
+        expect.extend_from_slice(&i64::to_le_bytes(3600));
+        expect.extend_from_slice(&u64::to_le_bytes(100));
+        expect.push(1);
+        expect.extend_from_slice(&u64::to_le_bytes(5_000));
         assert_eq!(packed, expect);
 
-        let check = FaucetInstruction::MintTokens { amount: 900 };
+        let check = FaucetInstruction::MintTokens {
+            amount: 900,
+            clamp_to_cap: false,
+        };
 
         let packed = check.pack();
         let mut expect = vec![1];
         expect.extend_from_slice(&u64::to_le_bytes(900));
+        expect.push(0);
         assert_eq!(packed, expect);
 
         let check = FaucetInstruction::CloseFaucet;
 
         let packed = check.pack();
         assert_eq!(packed, vec![2]);
+
+        let check = FaucetInstruction::UpdateFaucet {
+            new_admin: None,
+            new_amount: None,
+        };
+
+        let packed = check.pack();
+        assert_eq!(packed, vec![3, 0, 0]);
+
+        let check = FaucetInstruction::UpdateFaucet {
+            new_admin: Some(COption::None),
+            new_amount: None,
+        };
+
+        let packed = check.pack();
+        assert_eq!(packed, vec![3, 1, 0]);
+
+        let check = FaucetInstruction::UpdateFaucet {
+            new_admin: Some(COption::Some(Pubkey::try_from([1u8; 32].as_slice()).unwrap())),
+            new_amount: Some(900),
+        };
+
+        let packed = check.pack();
+        let mut expect = vec![3, 2];
+        expect.extend_from_slice(&[1u8; 32]);
+        expect.push(1);
+        expect.extend_from_slice(&u64::to_le_bytes(900));
+        assert_eq!(packed, expect);
+
+        let check = FaucetInstruction::SetPaused { paused: true };
+
+        let packed = check.pack();
+        assert_eq!(packed, vec![4, 1]);
     }
 }
@@ -0,0 +1,15 @@
+#![deny(missing_docs)]
+
+//! An SPL token faucet program: mints tokens to anyone, subject to a per-ix
+//! cap and a rolling per-claimant rate limit, with an admin able to bypass
+//! both and manage the faucet's configuration.
+
+pub mod error;
+pub mod instruction;
+pub mod processor;
+pub mod state;
+
+#[cfg(not(feature = "no-entrypoint"))]
+mod entrypoint;
+
+solana_program::declare_id!("FaucetSLPuPpReAAhyCsgg7hqfMa9NE9jnNJDuwAwDQ");
@@ -0,0 +1,386 @@
+//! Program instruction processor
+
+use std::convert::TryFrom;
+
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_option::COption,
+    program_pack::{IsInitialized, Pack},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+
+use crate::error::FaucetError;
+use crate::instruction::FaucetInstruction;
+use crate::state::{ClaimantRecord, Faucet, CLAIMANT_RECORD_SEED};
+
+/// Tag of the SPL Token program's `MintTo` instruction, per its stable wire format
+const TOKEN_IX_MINT_TO: u8 = 7;
+
+/// Program instruction processor
+pub struct Processor;
+
+impl Processor {
+    /// Processes a [FaucetInstruction](instruction.FaucetInstruction.html)
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
+        let instruction = FaucetInstruction::unpack(input)?;
+
+        match instruction {
+            FaucetInstruction::InitFaucet {
+                admin,
+                amount,
+                time_slice,
+                cap_per_slice,
+                max_total_minted,
+            } => Self::process_init_faucet(
+                accounts,
+                admin,
+                amount,
+                time_slice,
+                cap_per_slice,
+                max_total_minted,
+            ),
+            FaucetInstruction::MintTokens {
+                amount,
+                clamp_to_cap,
+            } => Self::process_mint_tokens(program_id, accounts, amount, clamp_to_cap),
+            FaucetInstruction::CloseFaucet => Self::process_close_faucet(accounts),
+            FaucetInstruction::UpdateFaucet {
+                new_admin,
+                new_amount,
+            } => Self::process_update_faucet(accounts, new_admin, new_amount),
+            FaucetInstruction::SetPaused { paused } => Self::process_set_paused(accounts, paused),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn process_init_faucet(
+        accounts: &[AccountInfo],
+        admin: COption<Pubkey>,
+        amount: u64,
+        time_slice: i64,
+        cap_per_slice: u64,
+        max_total_minted: COption<u64>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let _current_authority_info = next_account_info(account_info_iter)?;
+        let _mint_authority_info = next_account_info(account_info_iter)?;
+        let _mint_info = next_account_info(account_info_iter)?;
+        let faucet_info = next_account_info(account_info_iter)?;
+        let _token_program_info = next_account_info(account_info_iter)?;
+
+        let mut faucet = Faucet::unpack_unchecked(&faucet_info.data.borrow())?;
+        if faucet.is_initialized() {
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+
+        faucet.is_initialized = true;
+        faucet.admin = admin;
+        faucet.amount = amount;
+        faucet.time_slice = time_slice;
+        faucet.cap_per_slice = cap_per_slice;
+        faucet.max_total_minted = max_total_minted;
+        faucet.total_minted = 0;
+        faucet.paused = false;
+
+        Faucet::pack(faucet, &mut faucet_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_mint_tokens(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount: u64,
+        clamp_to_cap: bool,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let mint_authority_info = next_account_info(account_info_iter)?;
+        let faucet_info = next_account_info(account_info_iter)?;
+        let mint_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter).ok();
+        let payer_info = next_account_info(account_info_iter)?;
+        let claimant_record_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+        let clock_info = next_account_info(account_info_iter)?;
+        let memo_program_info = next_account_info(account_info_iter).ok();
+
+        let mut faucet = Faucet::unpack(&faucet_info.data.borrow())?;
+
+        if faucet.paused {
+            return Err(FaucetError::FaucetPaused.into());
+        }
+
+        let is_admin = match (faucet.admin, admin_info) {
+            (COption::Some(admin), Some(admin_info)) => {
+                admin_info.is_signer && *admin_info.key == admin
+            }
+            _ => false,
+        };
+
+        let mut amount = amount;
+        if !is_admin && amount > faucet.amount {
+            if !clamp_to_cap {
+                return Err(FaucetError::AmountExceedsCap.into());
+            }
+            let requested = amount;
+            amount = faucet.amount;
+            let memo_program_info = memo_program_info.ok_or(ProgramError::NotEnoughAccountKeys)?;
+            Self::emit_memo(
+                memo_program_info,
+                &format!("requested {}, capped to {}", requested, amount),
+            )?;
+        }
+
+        if !is_admin {
+            let clock = Clock::from_account_info(clock_info)?;
+            let claimant_owner = Self::token_account_owner(destination_info)?;
+            Self::enforce_rate_limit(
+                program_id,
+                &faucet,
+                faucet_info,
+                claimant_record_info,
+                &claimant_owner,
+                payer_info,
+                system_program_info,
+                &clock,
+                amount,
+            )?;
+        }
+
+        if let COption::Some(max_total_minted) = faucet.max_total_minted {
+            let total_minted_after = faucet
+                .total_minted
+                .checked_add(amount)
+                .ok_or(ProgramError::InvalidArgument)?;
+            if total_minted_after > max_total_minted {
+                return Err(FaucetError::SupplyCeilingReached.into());
+            }
+        }
+        faucet.total_minted = faucet
+            .total_minted
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+        Faucet::pack(faucet, &mut faucet_info.data.borrow_mut())?;
+
+        let (faucet_pda, bump_seed) =
+            Pubkey::find_program_address(&[faucet_info.key.as_ref()], program_id);
+        if faucet_pda != *mint_authority_info.key {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        let authority_signature_seeds = [faucet_info.key.as_ref(), &[bump_seed]];
+
+        let mint_to_ix = Self::build_mint_to_instruction(
+            token_program_info.key,
+            mint_info.key,
+            destination_info.key,
+            mint_authority_info.key,
+            amount,
+        );
+        invoke_signed(
+            &mint_to_ix,
+            &[
+                mint_info.clone(),
+                destination_info.clone(),
+                mint_authority_info.clone(),
+                token_program_info.clone(),
+            ],
+            &[&authority_signature_seeds],
+        )
+    }
+
+    /// Loads (lazily creating if needed) the claimant's rate-limit record, resets its window if
+    /// `time_slice` has elapsed, and rejects the mint if it would push the claimant over
+    /// `cap_per_slice` within the current window.
+    #[allow(clippy::too_many_arguments)]
+    fn enforce_rate_limit<'a>(
+        program_id: &Pubkey,
+        faucet: &Faucet,
+        faucet_info: &AccountInfo<'a>,
+        claimant_record_info: &AccountInfo<'a>,
+        claimant_owner: &Pubkey,
+        payer_info: &AccountInfo<'a>,
+        system_program_info: &AccountInfo<'a>,
+        clock: &Clock,
+        amount: u64,
+    ) -> ProgramResult {
+        let (claimant_record_pda, bump_seed) =
+            ClaimantRecord::find_address(faucet_info.key, claimant_owner, program_id);
+        if claimant_record_pda != *claimant_record_info.key {
+            return Err(ProgramError::InvalidSeeds);
+        }
+
+        let mut record = if claimant_record_info.data_is_empty() {
+            if !payer_info.is_signer {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            let rent = Rent::get()?;
+            let seeds = [
+                CLAIMANT_RECORD_SEED,
+                faucet_info.key.as_ref(),
+                claimant_owner.as_ref(),
+                &[bump_seed],
+            ];
+            invoke_signed(
+                &system_instruction::create_account(
+                    payer_info.key,
+                    claimant_record_info.key,
+                    rent.minimum_balance(ClaimantRecord::LEN),
+                    ClaimantRecord::LEN as u64,
+                    program_id,
+                ),
+                &[
+                    payer_info.clone(),
+                    claimant_record_info.clone(),
+                    system_program_info.clone(),
+                ],
+                &[&seeds],
+            )?;
+            ClaimantRecord {
+                is_initialized: true,
+                window_start: clock.unix_timestamp,
+                minted_in_window: 0,
+            }
+        } else {
+            ClaimantRecord::unpack(&claimant_record_info.data.borrow())?
+        };
+
+        if clock.unix_timestamp.saturating_sub(record.window_start) >= faucet.time_slice {
+            record.window_start = clock.unix_timestamp;
+            record.minted_in_window = 0;
+        }
+
+        let minted_after = record
+            .minted_in_window
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+        if minted_after > faucet.cap_per_slice {
+            msg!("Claimant rate limit exceeded for current time slice");
+            return Err(FaucetError::RateLimitExceeded.into());
+        }
+        record.minted_in_window = minted_after;
+
+        ClaimantRecord::pack(record, &mut claimant_record_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_close_faucet(accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let admin_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+        let faucet_info = next_account_info(account_info_iter)?;
+
+        let faucet = Faucet::unpack(&faucet_info.data.borrow())?;
+        match faucet.admin {
+            COption::Some(admin) if admin_info.is_signer && *admin_info.key == admin => {}
+            _ => return Err(ProgramError::MissingRequiredSignature),
+        }
+
+        let destination_starting_lamports = destination_info.lamports();
+        **destination_info.lamports.borrow_mut() = destination_starting_lamports
+            .checked_add(faucet_info.lamports())
+            .ok_or(ProgramError::InvalidArgument)?;
+        **faucet_info.lamports.borrow_mut() = 0;
+        faucet_info.data.borrow_mut().fill(0);
+
+        Ok(())
+    }
+
+    fn process_update_faucet(
+        accounts: &[AccountInfo],
+        new_admin: Option<COption<Pubkey>>,
+        new_amount: Option<u64>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let admin_info = next_account_info(account_info_iter)?;
+        let faucet_info = next_account_info(account_info_iter)?;
+
+        let mut faucet = Faucet::unpack(&faucet_info.data.borrow())?;
+        match faucet.admin {
+            COption::Some(admin) if admin_info.is_signer && *admin_info.key == admin => {}
+            _ => return Err(ProgramError::MissingRequiredSignature),
+        }
+
+        if let Some(new_admin) = new_admin {
+            faucet.admin = new_admin;
+        }
+        if let Some(new_amount) = new_amount {
+            faucet.amount = new_amount;
+        }
+
+        Faucet::pack(faucet, &mut faucet_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_set_paused(accounts: &[AccountInfo], paused: bool) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let admin_info = next_account_info(account_info_iter)?;
+        let faucet_info = next_account_info(account_info_iter)?;
+
+        let mut faucet = Faucet::unpack(&faucet_info.data.borrow())?;
+        match faucet.admin {
+            COption::Some(admin) if admin_info.is_signer && *admin_info.key == admin => {}
+            _ => return Err(ProgramError::MissingRequiredSignature),
+        }
+
+        faucet.paused = paused;
+
+        Faucet::pack(faucet, &mut faucet_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Reads the `owner` field out of an SPL Token account's raw data, to key the per-claimant
+    /// rate-limit record off the wallet being minted to rather than the token account itself.
+    /// The mint (bytes 0..32) is followed immediately by the owner (bytes 32..64) in every
+    /// version of the SPL Token account layout.
+    fn token_account_owner(token_account_info: &AccountInfo) -> Result<Pubkey, ProgramError> {
+        let data = token_account_info.data.borrow();
+        let owner_bytes = data.get(32..64).ok_or(ProgramError::InvalidAccountData)?;
+        Pubkey::try_from(owner_bytes).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// CPIs a human-readable memo to the SPL Memo program, whose instruction data is simply the
+    /// memo's UTF-8 bytes with no accompanying accounts required.
+    fn emit_memo(memo_program_info: &AccountInfo, memo: &str) -> ProgramResult {
+        let memo_ix = solana_program::instruction::Instruction {
+            program_id: *memo_program_info.key,
+            accounts: vec![],
+            data: memo.as_bytes().to_vec(),
+        };
+        invoke(&memo_ix, std::slice::from_ref(memo_program_info))
+    }
+
+    fn build_mint_to_instruction(
+        token_program_id: &Pubkey,
+        mint: &Pubkey,
+        destination: &Pubkey,
+        authority: &Pubkey,
+        amount: u64,
+    ) -> solana_program::instruction::Instruction {
+        let mut data = Vec::with_capacity(9);
+        data.push(TOKEN_IX_MINT_TO);
+        data.extend_from_slice(&amount.to_le_bytes());
+
+        solana_program::instruction::Instruction {
+            program_id: *token_program_id,
+            accounts: vec![
+                solana_program::instruction::AccountMeta::new(*mint, false),
+                solana_program::instruction::AccountMeta::new(*destination, false),
+                solana_program::instruction::AccountMeta::new_readonly(*authority, true),
+            ],
+            data,
+        }
+    }
+}
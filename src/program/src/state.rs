@@ -0,0 +1,202 @@
+//! Program state
+
+use std::convert::{TryFrom, TryInto};
+
+use solana_program::program_error::ProgramError;
+use solana_program::program_option::COption;
+use solana_program::program_pack::{IsInitialized, Pack, Sealed};
+use solana_program::pubkey::Pubkey;
+
+/// Seed prefix for the per-claimant rate-limit record PDA
+pub const CLAIMANT_RECORD_SEED: &[u8] = b"claimant";
+
+/// A faucet, created by `InitFaucet` and mutated by `MintTokens`/`UpdateFaucet`/`SetPaused`
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Faucet {
+    /// set once `InitFaucet` has run
+    pub is_initialized: bool,
+    /// an admin may mint any amount of tokens per ix, and alone may rotate this faucet's settings
+    pub admin: COption<Pubkey>,
+    /// all other accounts may only mint this amount per ix
+    pub amount: u64,
+    /// length, in seconds, of the rolling window used to rate-limit non-admin claimants
+    pub time_slice: i64,
+    /// total amount a single claimant may mint within one `time_slice`
+    pub cap_per_slice: u64,
+    /// hard lifetime cap on tokens minted by this faucet, unlimited if `COption::None`
+    pub max_total_minted: COption<u64>,
+    /// cumulative amount minted by this faucet since `InitFaucet`
+    pub total_minted: u64,
+    /// while `true`, `MintTokens` is rejected for everyone
+    pub paused: bool,
+}
+
+impl Sealed for Faucet {}
+
+impl IsInitialized for Faucet {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for Faucet {
+    const LEN: usize = 1 + 1 + 32 + 8 + 8 + 8 + (1 + 8) + 8 + 1;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let (&is_initialized, rest) = src.split_first().ok_or(ProgramError::InvalidAccountData)?;
+        let (admin, rest) = unpack_pubkey_option(rest)?;
+        let amount = unpack_u64(rest)?;
+        let rest = &rest[8..];
+        let time_slice = unpack_i64(rest)?;
+        let rest = &rest[8..];
+        let cap_per_slice = unpack_u64(rest)?;
+        let rest = &rest[8..];
+        let (max_total_minted, rest) = unpack_u64_coption(rest)?;
+        let total_minted = unpack_u64(rest)?;
+        let rest = &rest[8..];
+        let (&paused, _rest) = rest.split_first().ok_or(ProgramError::InvalidAccountData)?;
+
+        Ok(Self {
+            is_initialized: is_initialized != 0,
+            admin,
+            amount,
+            time_slice,
+            cap_per_slice,
+            max_total_minted,
+            total_minted,
+            paused: paused != 0,
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let mut buf = Vec::with_capacity(Self::LEN);
+        buf.push(self.is_initialized as u8);
+        pack_pubkey_option(&self.admin, &mut buf);
+        buf.extend_from_slice(&self.amount.to_le_bytes());
+        buf.extend_from_slice(&self.time_slice.to_le_bytes());
+        buf.extend_from_slice(&self.cap_per_slice.to_le_bytes());
+        pack_u64_coption(&self.max_total_minted, &mut buf);
+        buf.extend_from_slice(&self.total_minted.to_le_bytes());
+        buf.push(self.paused as u8);
+        dst[..buf.len()].copy_from_slice(&buf);
+    }
+}
+
+/// Tracks a single claimant's minting activity within the current rate-limit window
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClaimantRecord {
+    /// set once the record has been lazily created by a first `MintTokens`
+    pub is_initialized: bool,
+    /// start, in unix seconds, of the current rate-limit window
+    pub window_start: i64,
+    /// amount minted by this claimant since `window_start`
+    pub minted_in_window: u64,
+}
+
+impl Sealed for ClaimantRecord {}
+
+impl IsInitialized for ClaimantRecord {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for ClaimantRecord {
+    const LEN: usize = 1 + 8 + 8;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let (&is_initialized, rest) = src.split_first().ok_or(ProgramError::InvalidAccountData)?;
+        let window_start = unpack_i64(rest)?;
+        let rest = &rest[8..];
+        let minted_in_window = unpack_u64(rest)?;
+
+        Ok(Self {
+            is_initialized: is_initialized != 0,
+            window_start,
+            minted_in_window,
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let mut buf = Vec::with_capacity(Self::LEN);
+        buf.push(self.is_initialized as u8);
+        buf.extend_from_slice(&self.window_start.to_le_bytes());
+        buf.extend_from_slice(&self.minted_in_window.to_le_bytes());
+        dst[..buf.len()].copy_from_slice(&buf);
+    }
+}
+
+impl ClaimantRecord {
+    /// Derives the claimant rate-limit record address for `faucet` + `claimant_owner`
+    pub fn find_address(
+        faucet: &Pubkey,
+        claimant_owner: &Pubkey,
+        program_id: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[CLAIMANT_RECORD_SEED, faucet.as_ref(), claimant_owner.as_ref()],
+            program_id,
+        )
+    }
+}
+
+fn unpack_u64(input: &[u8]) -> Result<u64, ProgramError> {
+    input
+        .get(..8)
+        .and_then(|slice| slice.try_into().ok())
+        .map(u64::from_le_bytes)
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+fn unpack_i64(input: &[u8]) -> Result<i64, ProgramError> {
+    input
+        .get(..8)
+        .and_then(|slice| slice.try_into().ok())
+        .map(i64::from_le_bytes)
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+fn unpack_pubkey_option(input: &[u8]) -> Result<(COption<Pubkey>, &[u8]), ProgramError> {
+    match input.split_first() {
+        Some((&0, rest)) => Ok((COption::None, rest)),
+        Some((&1, rest)) if rest.len() >= 32 => {
+            let (key, rest) = rest.split_at(32);
+            let pubkey = Pubkey::try_from(key).map_err(|_| ProgramError::InvalidAccountData)?;
+            Ok((COption::Some(pubkey), rest))
+        }
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+fn pack_pubkey_option(value: &COption<Pubkey>, buf: &mut Vec<u8>) {
+    match *value {
+        COption::Some(ref key) => {
+            buf.push(1);
+            buf.extend_from_slice(&key.to_bytes());
+        }
+        COption::None => buf.push(0),
+    }
+}
+
+fn unpack_u64_coption(input: &[u8]) -> Result<(COption<u64>, &[u8]), ProgramError> {
+    match input.split_first() {
+        Some((&0, rest)) => Ok((COption::None, rest)),
+        Some((&1, rest)) => {
+            let value = unpack_u64(rest)?;
+            Ok((COption::Some(value), &rest[8..]))
+        }
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+fn pack_u64_coption(value: &COption<u64>, buf: &mut Vec<u8>) {
+    match *value {
+        COption::Some(amount) => {
+            buf.push(1);
+            buf.extend_from_slice(&amount.to_le_bytes());
+        }
+        COption::None => buf.push(0),
+    }
+}